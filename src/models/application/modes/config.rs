@@ -0,0 +1,92 @@
+use fragment;
+use helpers::SelectableVec;
+use std::fmt;
+use std::slice::Iter;
+use models::application::modes::{SearchSelectMode, MAX_SEARCH_SELECT_RESULTS};
+use models::application::preferences::OptionDoc;
+
+pub struct ConfigMode {
+    insert: bool,
+    input: String,
+    options: Vec<String>,
+    results: SelectableVec<String>,
+}
+
+impl ConfigMode {
+    /// Builds a `:config` listing, pairing each documented option with
+    /// its current effective value (in the same order as `docs`).
+    pub fn new(docs: Vec<OptionDoc>, effective_values: Vec<String>) -> ConfigMode {
+        let options = docs.iter()
+            .zip(effective_values.iter())
+            .map(|(doc, value)| {
+                     format!("{} {} = {} (default: {}) - {}",
+                             doc.name,
+                             doc.type_hint,
+                             value,
+                             doc.default,
+                             doc.description)
+                 })
+            .collect();
+
+        ConfigMode {
+            insert: true,
+            input: String::new(),
+            options: options,
+            results: SelectableVec::new(Vec::new()),
+        }
+    }
+}
+
+impl fmt::Display for ConfigMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CONFIG")
+    }
+}
+
+impl SearchSelectMode<String> for ConfigMode {
+    fn search(&mut self) {
+        // Find the options we're looking for using the query.
+        let results = fragment::matching::find(&self.input, &self.options, MAX_SEARCH_SELECT_RESULTS);
+
+        // We don't care about the result objects; we just want
+        // the underlying symbols. Map the collection to get these.
+        self.results = SelectableVec::new(
+            results
+            .into_iter()
+            .map(|r| r.clone())
+            .collect()
+        );
+    }
+
+    fn query(&mut self) -> &mut String {
+        &mut self.input
+    }
+
+    fn insert_mode(&self) -> bool {
+        self.insert
+    }
+
+    fn set_insert_mode(&mut self, insert_mode: bool) {
+        self.insert = insert_mode;
+    }
+
+    fn results(&self) -> Iter<String> {
+        self.results.iter()
+    }
+
+    fn selection(&self) -> Option<&String> {
+        self.results.selection()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.results.selected_index()
+    }
+
+    fn select_previous(&mut self) {
+        self.results.select_previous();
+    }
+
+    fn select_next(&mut self) {
+        self.results.select_next();
+    }
+}
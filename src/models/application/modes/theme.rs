@@ -9,17 +9,40 @@ pub struct ThemeMode {
     input: String,
     themes: Vec<String>,
     results: SelectableVec<String>,
+    original: String,
+    preview: Option<String>,
 }
 
 impl ThemeMode {
-    pub fn new(themes: Vec<String>) -> ThemeMode {
+    pub fn new(themes: Vec<String>, current_theme: String) -> ThemeMode {
         ThemeMode {
             insert: true,
             input: String::new(),
             themes: themes,
             results: SelectableVec::new(Vec::new()),
+            original: current_theme,
+            preview: None,
         }
     }
+
+    /// The theme that was active when this mode was opened. The
+    /// renderer should re-apply this if the mode is cancelled without
+    /// a confirmed selection, undoing any previewed theme.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// The theme the renderer should apply right now: whichever result
+    /// is currently highlighted, updated on every `search`/`select_next`/
+    /// `select_previous` call so moving the cursor previews themes live
+    /// instead of waiting for a confirmed selection.
+    pub fn preview(&self) -> Option<&str> {
+        self.preview.as_ref().map(|theme| theme.as_str())
+    }
+
+    fn update_preview(&mut self) {
+        self.preview = self.results.selection().cloned();
+    }
 }
 
 impl fmt::Display for ThemeMode {
@@ -41,6 +64,7 @@ impl SearchSelectMode<String> for ThemeMode {
             .map(|r| r.clone())
             .collect()
         );
+        self.update_preview();
     }
 
     fn query(&mut self) -> &mut String {
@@ -69,9 +93,54 @@ impl SearchSelectMode<String> for ThemeMode {
 
     fn select_previous(&mut self) {
         self.results.select_previous();
+        self.update_preview();
     }
 
     fn select_next(&mut self) {
         self.results.select_next();
+        self.update_preview();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThemeMode;
+    use models::application::modes::SearchSelectMode;
+
+    #[test]
+    fn moving_the_cursor_changes_the_previewed_selection() {
+        let themes = vec![String::from("solarized_dark"), String::from("solarized_light")];
+        let mut mode = ThemeMode::new(themes, String::from("solarized_dark"));
+        mode.search();
+
+        assert_eq!(mode.selection(), Some(&String::from("solarized_dark")));
+        assert_eq!(mode.preview(), Some("solarized_dark"));
+
+        mode.select_next();
+
+        assert_eq!(mode.selection(), Some(&String::from("solarized_light")));
+        assert_eq!(mode.preview(), Some("solarized_light"));
+
+        mode.select_previous();
+
+        assert_eq!(mode.preview(), Some("solarized_dark"));
+    }
+
+    #[test]
+    fn preview_is_none_before_a_search_has_produced_any_results() {
+        let themes = vec![String::from("solarized_dark")];
+        let mode = ThemeMode::new(themes, String::from("solarized_dark"));
+
+        assert_eq!(mode.preview(), None);
+    }
+
+    #[test]
+    fn cancelling_yields_back_the_original_theme() {
+        let themes = vec![String::from("solarized_dark"), String::from("solarized_light")];
+        let mut mode = ThemeMode::new(themes, String::from("solarized_dark"));
+        mode.search();
+        mode.select_next();
+
+        assert_eq!(mode.original(), "solarized_dark");
     }
 }
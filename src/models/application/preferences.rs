@@ -1,11 +1,16 @@
 use errors::*;
 use app_dirs::{app_root, AppDataType, AppInfo};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher, DebouncedEvent};
+use std::fmt;
 use std::fs::OpenOptions;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
 use yaml::yaml::{Yaml, YamlLoader};
 
 const FILE_NAME: &'static str = "config.yml";
+const PROJECT_FILE_NAME: &'static str = ".amp.yml";
 const APP_INFO: AppInfo = AppInfo {
     name: "amp",
     author: "Jordan MacDonald",
@@ -17,28 +22,300 @@ const LINE_LENGTH_GUIDE_KEY: &'static str = "line_length_guide";
 const LINE_WRAPPING_KEY: &'static str = "line_wrapping";
 const SOFT_TABS_KEY: &'static str = "soft_tabs";
 
-const THEME_DEFAULT: &'static str = "solarized_dark";
-const TAB_WIDTH_DEFAULT: usize = 2;
-const LINE_LENGTH_GUIDE_DEFAULT: usize = 80;
-const LINE_WRAPPING_DEFAULT: bool = true;
-const SOFT_TABS_DEFAULT: bool = true;
-
 pub struct Preferences {
     data: Option<Yaml>,
+    warnings: Vec<ConfigWarning>,
+}
+
+/// An unrecognized key or wrong-typed value found in `config.yml`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConfigWarning {
+    pub path: String,
+    pub expected: &'static str,
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: expected {}, ignoring", self.path, self.expected)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ValueType {
+    String,
+    PositiveInteger,
+    IntegerOrBoolean,
+    Boolean,
+    TypeMap,
+}
+
+impl ValueType {
+    fn describe(&self) -> &'static str {
+        match *self {
+            ValueType::String => "a string",
+            ValueType::PositiveInteger => "a positive integer",
+            ValueType::IntegerOrBoolean => "an integer or boolean",
+            ValueType::Boolean => "a boolean",
+            ValueType::TypeMap => "a map of file extensions to settings",
+        }
+    }
+
+    fn matches(&self, value: &Yaml) -> bool {
+        match (*self, value) {
+            (ValueType::String, &Yaml::String(_)) => true,
+            (ValueType::PositiveInteger, &Yaml::Integer(n)) => n > 0,
+            (ValueType::IntegerOrBoolean, &Yaml::Integer(_)) => true,
+            (ValueType::IntegerOrBoolean, &Yaml::Boolean(_)) => true,
+            (ValueType::Boolean, &Yaml::Boolean(_)) => true,
+            (ValueType::TypeMap, &Yaml::Hash(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn hint(&self) -> &'static str {
+        match *self {
+            ValueType::String => "<string>",
+            ValueType::PositiveInteger => "<unsigned integer>",
+            ValueType::IntegerOrBoolean => "<unsigned integer or boolean>",
+            ValueType::Boolean => "<boolean>",
+            ValueType::TypeMap => "<map>",
+        }
+    }
+}
+
+/// A preference's default value, typed to match its `ValueType`.
+#[derive(Clone, Copy)]
+enum DefaultValue {
+    Str(&'static str),
+    UnsignedInt(usize),
+    Bool(bool),
+}
+
+impl DefaultValue {
+    fn to_string(&self) -> String {
+        match *self {
+            DefaultValue::Str(value) => value.to_string(),
+            DefaultValue::UnsignedInt(value) => value.to_string(),
+            DefaultValue::Bool(value) => value.to_string(),
+        }
+    }
+}
+
+/// Documents a single preference, for `:config` to list.
+pub struct OptionDoc {
+    pub name: &'static str,
+    pub type_hint: &'static str,
+    pub default: String,
+    pub description: &'static str,
+}
+
+struct OptionMeta {
+    key: &'static str,
+    value_type: ValueType,
+    default: DefaultValue,
+    description: &'static str,
+}
+
+// The single source of truth for every user-facing preference.
+const OPTIONS: &'static [OptionMeta] =
+    &[OptionMeta {
+          key: THEME_KEY,
+          value_type: ValueType::String,
+          default: DefaultValue::Str("solarized_dark"),
+          description: "Colour scheme applied to the editor and its interface.",
+      },
+      OptionMeta {
+          key: TAB_WIDTH_KEY,
+          value_type: ValueType::PositiveInteger,
+          default: DefaultValue::UnsignedInt(2),
+          description: "Number of columns a tab character/soft tab occupies.",
+      },
+      OptionMeta {
+          key: LINE_LENGTH_GUIDE_KEY,
+          value_type: ValueType::IntegerOrBoolean,
+          default: DefaultValue::UnsignedInt(80),
+          description: "Column at which to render a line length guide; `true` uses the default column, and the guide is off when unset or `false`.",
+      },
+      OptionMeta {
+          key: LINE_WRAPPING_KEY,
+          value_type: ValueType::Boolean,
+          default: DefaultValue::Bool(true),
+          description: "Whether long lines wrap onto the next screen line.",
+      },
+      OptionMeta {
+          key: SOFT_TABS_KEY,
+          value_type: ValueType::Boolean,
+          default: DefaultValue::Bool(true),
+          description: "Whether to insert spaces instead of a tab character.",
+      }];
+
+/// Watches the user's `config.yml` for changes.
+pub struct PreferencesWatcher {
+    // Kept alive for as long as we want to keep receiving events; dropping
+    // it stops the underlying filesystem watch.
+    _watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+}
+
+impl PreferencesWatcher {
+    pub fn new(config_path: &PathBuf) -> Result<PreferencesWatcher> {
+        let (sender, events) = channel();
+        let mut watcher: RecommendedWatcher = Watcher::new(sender, Duration::from_millis(500))
+            .chain_err(|| "Couldn't create a config file watcher")?;
+        watcher
+            .watch(config_path, RecursiveMode::NonRecursive)
+            .chain_err(|| "Couldn't watch config file for changes")?;
+
+        Ok(PreferencesWatcher {
+               _watcher: watcher,
+               events: events,
+           })
+    }
+
+    /// Non-blocking check for pending config file changes.
+    pub fn dirty(&self) -> bool {
+        PreferencesWatcher::drain_dirty(&self.events)
+    }
+
+    // Folds pending events down to a single dirty flag; split out from
+    // `dirty` so the debounce/coalesce logic can be fed events directly
+    // in tests, without a real filesystem watch.
+    fn drain_dirty(events: &Receiver<DebouncedEvent>) -> bool {
+        let mut dirty = false;
+
+        while let Ok(event) = events.try_recv() {
+            match event {
+                DebouncedEvent::Write(_) |
+                DebouncedEvent::Create(_) => dirty = true,
+                _ => (),
+            }
+        }
+
+        dirty
+    }
 }
 
 impl Preferences {
+    /// Builds a `Preferences` from an already-parsed document, validating it immediately.
     pub fn new(data: Option<Yaml>) -> Preferences {
-        Preferences { data: data }
+        let warnings = match data {
+            Some(ref data) => Preferences::validate(data),
+            None => Vec::new(),
+        };
+
+        Preferences {
+            data: data,
+            warnings: warnings,
+        }
     }
 
+    /// Loads the user-level config, validating it against the expected schema.
     pub fn load() -> Result<Preferences> {
-        // Build a path to the config file.
+        let config_path = Preferences::config_path()?;
+        let document = Preferences::read_document(&config_path)?;
+
+        Ok(Preferences::new(document))
+    }
+
+    /// Loads the user-level config, deep-merging a project-local `.amp.yml` over it, if found.
+    pub fn load_with_project(cwd: &Path) -> Result<Preferences> {
+        let config_path = Preferences::config_path()?;
+        let user_document = Preferences::read_document(&config_path)?;
+
+        let data = match Preferences::find_project_file(cwd) {
+            Some(project_path) => {
+                let project_document = Preferences::read_document(&project_path)?;
+
+                Preferences::merge(user_document, project_document)
+            }
+            None => user_document,
+        };
+
+        Ok(Preferences::new(data))
+    }
+
+    fn find_project_file(start: &Path) -> Option<PathBuf> {
+        let mut dir = Some(start);
+
+        while let Some(current) = dir {
+            let candidate = current.join(PROJECT_FILE_NAME);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+
+            dir = current.parent();
+        }
+
+        None
+    }
+
+    /// Recursively merges two optional YAML documents, `override_data` taking precedence.
+    fn merge(base: Option<Yaml>, override_data: Option<Yaml>) -> Option<Yaml> {
+        match (base, override_data) {
+            (Some(base), Some(override_data)) => Some(Preferences::merge_yaml(base, override_data)),
+            (Some(base), None) => Some(base),
+            (None, Some(override_data)) => Some(override_data),
+            (None, None) => None,
+        }
+    }
+
+    fn merge_yaml(base: Yaml, override_data: Yaml) -> Yaml {
+        match (base, override_data) {
+            (Yaml::Hash(base_hash), Yaml::Hash(override_hash)) => {
+                let mut merged = base_hash;
+
+                for (key, override_value) in override_hash {
+                    let merged_value = match merged.remove(&key) {
+                        Some(base_value) => Preferences::merge_yaml(base_value, override_value),
+                        None => override_value,
+                    };
+
+                    merged.insert(key, merged_value);
+                }
+
+                Yaml::Hash(merged)
+            }
+            (_, override_data) => override_data,
+        }
+    }
+
+    /// Spawns a filesystem watcher on the resolved config path.
+    pub fn watch(&self) -> Result<PreferencesWatcher> {
+        let config_path = Preferences::config_path()?;
+
+        PreferencesWatcher::new(&config_path)
+    }
+
+    /// Re-reads and re-parses the config file, keeping the last-known-good document on failure.
+    pub fn reload(&mut self) -> Result<()> {
+        let config_path = Preferences::config_path()?;
+
+        self.reload_from(&config_path)
+    }
+
+    /// Does the actual work of `reload`, against an explicit path, for testability.
+    fn reload_from(&mut self, config_path: &PathBuf) -> Result<()> {
+        let document = Preferences::read_document(config_path)?;
+
+        self.warnings = match document {
+            Some(ref data) => Preferences::validate(data),
+            None => Vec::new(),
+        };
+        self.data = document;
+
+        Ok(())
+    }
+
+    fn config_path() -> Result<PathBuf> {
         let mut config_path =
             app_root(AppDataType::UserConfig, &APP_INFO)
                 .chain_err(|| "Couldn't create or open application config directory")?;
         config_path.push(FILE_NAME);
 
+        Ok(config_path)
+    }
+
+    fn read_document(config_path: &PathBuf) -> Result<Option<Yaml>> {
         // Open (or create) the config file.
         let mut config_file = OpenOptions::new()
             .read(true)
@@ -56,93 +333,253 @@ impl Preferences {
         // Parse the config file's contents and get the first YAML document inside.
         let parsed_data = YamlLoader::load_from_str(&data)
             .chain_err(|| "Couldn't parse config file")?;
-        let document = parsed_data.into_iter().nth(0);
 
-        Ok(Preferences { data: document })
+        Ok(parsed_data.into_iter().nth(0))
+    }
+
+    /// Describes every available preference, for `:config` to list.
+    pub fn describe() -> Vec<OptionDoc> {
+        OPTIONS
+            .iter()
+            .map(|option| {
+                     OptionDoc {
+                         name: option.key,
+                         type_hint: option.value_type.hint(),
+                         default: option.default.to_string(),
+                         description: option.description,
+                     }
+                 })
+            .collect()
+    }
+
+    /// The current effective value of every preference in `describe`'s order.
+    pub fn effective_values(&self) -> Vec<String> {
+        vec![self.theme().to_string(),
+             self.tab_width(None).to_string(),
+             match self.line_length_guide(None) {
+                 Some(length) => length.to_string(),
+                 None => "false".to_string(),
+             },
+             self.line_wrapping(None).to_string(),
+             self.soft_tabs(None).to_string()]
+    }
+
+    /// Every unknown key or wrong-typed value currently in the config file.
+    pub fn warnings(&self) -> &[ConfigWarning] {
+        &self.warnings
+    }
+
+    fn validate(data: &Yaml) -> Vec<ConfigWarning> {
+        let mut warnings = Vec::new();
+
+        if let Yaml::Hash(ref hash) = *data {
+            for (key, value) in hash.iter() {
+                let key_name = match *key {
+                    Yaml::String(ref s) => s.clone(),
+                    _ => continue,
+                };
+
+                if key_name == TYPES_KEY {
+                    if let Yaml::Hash(ref types_hash) = *value {
+                        for (extension, sub_config) in types_hash.iter() {
+                            if let Yaml::String(ref extension_name) = *extension {
+                                warnings.extend(Preferences::validate_type_config(extension_name, sub_config));
+                            }
+                        }
+                    } else {
+                        warnings.push(ConfigWarning {
+                                            path: key_name,
+                                            expected: ValueType::TypeMap.describe(),
+                                        });
+                    }
+                    continue;
+                }
+
+                match OPTIONS.iter().find(|option| option.key == key_name) {
+                    Some(option) => {
+                        if !option.value_type.matches(value) {
+                            warnings.push(ConfigWarning {
+                                                path: key_name,
+                                                expected: option.value_type.describe(),
+                                            });
+                        }
+                    }
+                    None => {
+                        warnings.push(ConfigWarning {
+                                            path: key_name,
+                                            expected: "a known configuration key",
+                                        });
+                    }
+                }
+            }
+        }
+
+        warnings
+    }
+
+    fn validate_type_config(extension: &str, data: &Yaml) -> Vec<ConfigWarning> {
+        let mut warnings = Vec::new();
+
+        if let Yaml::Hash(ref hash) = *data {
+            for (key, value) in hash.iter() {
+                let key_name = match *key {
+                    Yaml::String(ref s) => s.clone(),
+                    _ => continue,
+                };
+
+                // `theme` isn't overridable per file extension.
+                match OPTIONS.iter().find(|option| option.key == key_name && option.key != THEME_KEY) {
+                    Some(option) => {
+                        if !option.value_type.matches(value) {
+                            warnings.push(ConfigWarning {
+                                                path: format!("{}.{}.{}", TYPES_KEY, extension, key_name),
+                                                expected: option.value_type.describe(),
+                                            });
+                        }
+                    }
+                    None => {
+                        warnings.push(ConfigWarning {
+                                            path: format!("{}.{}.{}", TYPES_KEY, extension, key_name),
+                                            expected: "a known configuration key",
+                                        });
+                    }
+                }
+            }
+        } else {
+            warnings.push(ConfigWarning {
+                                path: format!("{}.{}", TYPES_KEY, extension),
+                                expected: "a map of settings",
+                            });
+        }
+
+        warnings
+    }
+
+    fn option(key: &str) -> &'static OptionMeta {
+        OPTIONS
+            .iter()
+            .find(|option| option.key == key)
+            .expect("unknown preference key")
+    }
+
+    fn default_str(key: &str) -> &'static str {
+        match Preferences::option(key).default {
+            DefaultValue::Str(value) => value,
+            _ => "",
+        }
+    }
+
+    fn default_usize(key: &str) -> usize {
+        match Preferences::option(key).default {
+            DefaultValue::UnsignedInt(value) => value,
+            _ => 0,
+        }
+    }
+
+    fn default_bool(key: &str) -> bool {
+        match Preferences::option(key).default {
+            DefaultValue::Bool(value) => value,
+            _ => false,
+        }
     }
 
     pub fn theme(&self) -> &str {
-        self.data
-            .as_ref()
-            .and_then(|data| if let Yaml::String(ref theme) = data[THEME_KEY] {
-                          Some(theme.as_str())
-                      } else {
-                          None
-                      })
-            .unwrap_or(THEME_DEFAULT)
+        match self.lookup(THEME_KEY, None) {
+            Some(&Yaml::String(ref theme)) => theme.as_str(),
+            _ => Preferences::default_str(THEME_KEY),
+        }
     }
 
     pub fn tab_width(&self, path: Option<&PathBuf>) -> usize {
-        self.data
-            .as_ref()
-            .and_then(|data| {
-                if let Some(extension) = path.and_then(|p| p.extension()).and_then(|e| e.to_str()) {
-                    if let Yaml::Integer(tab_width) = data[TYPES_KEY][extension][TAB_WIDTH_KEY] {
-                        return Some(tab_width as usize);
-                    } else if let Yaml::Integer(tab_width) = data[TAB_WIDTH_KEY] {
-                        return Some(tab_width as usize);
-                    }
-                } else if let Yaml::Integer(tab_width) = data[TAB_WIDTH_KEY] {
-                    return Some(tab_width as usize);
+        match self.lookup(TAB_WIDTH_KEY, path) {
+            Some(&Yaml::Integer(tab_width)) => tab_width as usize,
+            _ => Preferences::default_usize(TAB_WIDTH_KEY),
+        }
+    }
+
+    pub fn line_length_guide(&self, path: Option<&PathBuf>) -> Option<usize> {
+        match self.lookup(LINE_LENGTH_GUIDE_KEY, path) {
+            Some(&Yaml::Integer(line_length)) => Some(line_length as usize),
+            Some(&Yaml::Boolean(line_length_guide)) => {
+                if line_length_guide {
+                    Some(Preferences::default_usize(LINE_LENGTH_GUIDE_KEY))
+                } else {
+                    None
                 }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn line_wrapping(&self, path: Option<&PathBuf>) -> bool {
+        match self.lookup(LINE_WRAPPING_KEY, path) {
+            Some(&Yaml::Boolean(wrapping)) => wrapping,
+            _ => Preferences::default_bool(LINE_WRAPPING_KEY),
+        }
+    }
+
+    pub fn soft_tabs(&self, path: Option<&PathBuf>) -> bool {
+        match self.lookup(SOFT_TABS_KEY, path) {
+            Some(&Yaml::Boolean(soft_tabs)) => soft_tabs,
+            _ => Preferences::default_bool(SOFT_TABS_KEY),
+        }
+    }
 
-                None
-            })
-            .unwrap_or(TAB_WIDTH_DEFAULT)
-    }
-
-    pub fn line_length_guide(&self) -> Option<usize> {
-        self.data
-            .as_ref()
-            .and_then(|data| match data[LINE_LENGTH_GUIDE_KEY] {
-                          Yaml::Integer(line_length) => Some(line_length as usize),
-                          Yaml::Boolean(line_length_guide) => {
-                              if line_length_guide {
-                                  Some(LINE_LENGTH_GUIDE_DEFAULT)
-                              } else {
-                                  None
-                              }
-                          }
-                          _ => None,
-                      })
-    }
-
-    pub fn line_wrapping(&self) -> bool {
-        self.data
-            .as_ref()
-            .and_then(|data| if let Yaml::Boolean(wrapping) = data[LINE_WRAPPING_KEY] {
-                          Some(wrapping)
-                      } else {
-                          None
-                      })
-            .unwrap_or(LINE_WRAPPING_DEFAULT)
-    }
-
-    pub fn soft_tabs(&self) -> bool {
-        self.data
-            .as_ref()
-            .and_then(|data| if let Yaml::Boolean(soft_tabs) = data[SOFT_TABS_KEY] {
-                          Some(soft_tabs)
-                      } else {
-                          None
-                      })
-            .unwrap_or(SOFT_TABS_DEFAULT)
-    }
-
-    pub fn tab_content(&self) -> String {
-        if self.soft_tabs() {
-            format!("{:1$}", "", self.tab_width(None))
+    pub fn tab_content(&self, path: Option<&PathBuf>) -> String {
+        if self.soft_tabs(path) {
+            format!("{:1$}", "", self.tab_width(path))
         } else {
             String::from("\t")
         }
     }
+
+    /// Resolves `key`, preferring a `types[extension][key]` override over the top-level `key`.
+    fn lookup(&self, key: &str, path: Option<&PathBuf>) -> Option<&Yaml> {
+        let data = match self.data {
+            Some(ref data) => data,
+            None => return None,
+        };
+        let value_type = Preferences::option(key).value_type;
+
+        if let Some(extension) = path.and_then(|p| p.extension()).and_then(|e| e.to_str()) {
+            let scoped = &data[TYPES_KEY][extension][key];
+            if value_type.matches(scoped) {
+                return Some(scoped);
+            }
+        }
+
+        let value = &data[key];
+        if value_type.matches(value) {
+            Some(value)
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Preferences, YamlLoader};
+    use super::{Preferences, PreferencesWatcher, YamlLoader};
     use std::path::PathBuf;
 
+    #[test]
+    fn dirty_folds_write_and_create_events_to_true_and_ignores_others() {
+        use std::sync::mpsc::channel;
+        use notify::DebouncedEvent;
+
+        let (sender, events) = channel();
+
+        sender.send(DebouncedEvent::NoticeWrite(PathBuf::from("config.yml"))).unwrap();
+        assert!(!PreferencesWatcher::drain_dirty(&events));
+
+        sender.send(DebouncedEvent::Write(PathBuf::from("config.yml"))).unwrap();
+        assert!(PreferencesWatcher::drain_dirty(&events));
+        assert!(!PreferencesWatcher::drain_dirty(&events));
+
+        sender.send(DebouncedEvent::Create(PathBuf::from("config.yml"))).unwrap();
+        assert!(PreferencesWatcher::drain_dirty(&events));
+    }
+
     #[test]
     fn preferences_returns_user_defined_theme_name() {
         let data = YamlLoader::load_from_str("theme: \"my_theme\"").unwrap();
@@ -178,12 +615,22 @@ mod tests {
                    12);
     }
 
+    #[test]
+    fn tab_width_falls_back_to_top_level_value_when_type_specific_value_has_wrong_type() {
+        let data = YamlLoader::load_from_str("tab_width: 12\ntypes:\n  rs:\n    tab_width: \"four\"")
+            .unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        assert_eq!(preferences.tab_width(Some(PathBuf::from("preferences.rs")).as_ref()),
+                   12);
+    }
+
     #[test]
     fn preferences_returns_user_defined_line_length_guide() {
         let data = YamlLoader::load_from_str("line_length_guide: 100").unwrap();
         let preferences = Preferences::new(data.into_iter().nth(0));
 
-        assert_eq!(preferences.line_length_guide(), Some(100));
+        assert_eq!(preferences.line_length_guide(None), Some(100));
     }
 
     #[test]
@@ -191,7 +638,7 @@ mod tests {
         let data = YamlLoader::load_from_str("line_length_guide: false").unwrap();
         let preferences = Preferences::new(data.into_iter().nth(0));
 
-        assert_eq!(preferences.line_length_guide(), None);
+        assert_eq!(preferences.line_length_guide(None), None);
     }
 
     #[test]
@@ -199,7 +646,7 @@ mod tests {
         let data = YamlLoader::load_from_str("line_length_guide: true").unwrap();
         let preferences = Preferences::new(data.into_iter().nth(0));
 
-        assert_eq!(preferences.line_length_guide(), Some(80));
+        assert_eq!(preferences.line_length_guide(None), Some(80));
     }
 
     #[test]
@@ -207,7 +654,7 @@ mod tests {
         let data = YamlLoader::load_from_str("line_wrapping: false").unwrap();
         let preferences = Preferences::new(data.into_iter().nth(0));
 
-        assert_eq!(preferences.line_wrapping(), false);
+        assert_eq!(preferences.line_wrapping(None), false);
     }
 
     #[test]
@@ -215,7 +662,7 @@ mod tests {
         let data = YamlLoader::load_from_str("soft_tabs: false").unwrap();
         let preferences = Preferences::new(data.into_iter().nth(0));
 
-        assert_eq!(preferences.soft_tabs(), false);
+        assert_eq!(preferences.soft_tabs(None), false);
     }
 
     #[test]
@@ -223,7 +670,7 @@ mod tests {
         let data = YamlLoader::load_from_str("soft_tabs: true\ntab_width: 5").unwrap();
         let preferences = Preferences::new(data.into_iter().nth(0));
 
-        assert_eq!(preferences.tab_content(), "     ");
+        assert_eq!(preferences.tab_content(None), "     ");
     }
 
     #[test]
@@ -231,6 +678,174 @@ mod tests {
         let data = YamlLoader::load_from_str("soft_tabs: false\ntab_width: 5").unwrap();
         let preferences = Preferences::new(data.into_iter().nth(0));
 
-        assert_eq!(preferences.tab_content(), "\t");
+        assert_eq!(preferences.tab_content(None), "\t");
+    }
+
+    #[test]
+    fn soft_tabs_returns_user_defined_type_specific_data() {
+        let data = YamlLoader::load_from_str("soft_tabs: true\ntypes:\n  rs:\n    soft_tabs: false")
+            .unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        assert_eq!(preferences.soft_tabs(Some(PathBuf::from("preferences.rs")).as_ref()),
+                   false);
+    }
+
+    #[test]
+    fn soft_tabs_returns_default_when_user_defined_type_specific_data_not_found() {
+        let data = YamlLoader::load_from_str("soft_tabs: true").unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        assert_eq!(preferences.soft_tabs(Some(PathBuf::from("preferences.rs")).as_ref()),
+                   true);
+    }
+
+    #[test]
+    fn merge_prefers_override_scalar_values_over_base_values() {
+        let base = YamlLoader::load_from_str("theme: \"base_theme\"\ntab_width: 2")
+            .unwrap()
+            .into_iter()
+            .nth(0);
+        let project = YamlLoader::load_from_str("theme: \"project_theme\"")
+            .unwrap()
+            .into_iter()
+            .nth(0);
+        let preferences = Preferences::new(Preferences::merge(base, project));
+
+        assert_eq!(preferences.theme(), "project_theme");
+        assert_eq!(preferences.tab_width(None), 2);
+    }
+
+    #[test]
+    fn merge_deep_merges_nested_type_specific_overrides_instead_of_clobbering() {
+        let base = YamlLoader::load_from_str("types:\n  rs:\n    tab_width: 2\n  go:\n    tab_width: 4")
+            .unwrap()
+            .into_iter()
+            .nth(0);
+        let project = YamlLoader::load_from_str("types:\n  rs:\n    tab_width: 8")
+            .unwrap()
+            .into_iter()
+            .nth(0);
+        let preferences = Preferences::new(Preferences::merge(base, project));
+
+        assert_eq!(preferences.tab_width(Some(PathBuf::from("main.rs")).as_ref()), 8);
+        assert_eq!(preferences.tab_width(Some(PathBuf::from("main.go")).as_ref()), 4);
+    }
+
+    #[test]
+    fn find_project_file_walks_up_to_the_first_match() {
+        use std::env;
+        use std::fs;
+
+        let root = env::temp_dir().join(format!("amp-preferences-test-{}", line!()));
+        let nested = root.join("src/models");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".amp.yml"), "theme: \"project_theme\"").unwrap();
+
+        let found = Preferences::find_project_file(&nested);
+
+        assert_eq!(found, Some(root.join(".amp.yml")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn reload_from_keeps_the_last_known_good_document_when_a_reload_fails_to_parse() {
+        use std::env;
+        use std::fs;
+
+        let config_path = env::temp_dir().join(format!("amp-preferences-test-{}", line!()));
+        fs::write(&config_path, "theme: \"good_theme\"").unwrap();
+
+        let mut preferences = Preferences::new(YamlLoader::load_from_str("theme: \"good_theme\"")
+                                                     .unwrap()
+                                                     .into_iter()
+                                                     .nth(0));
+
+        fs::write(&config_path, "theme: \"broken: [").unwrap();
+
+        assert!(preferences.reload_from(&config_path).is_err());
+        assert_eq!(preferences.theme(), "good_theme");
+
+        fs::remove_file(&config_path).unwrap();
+    }
+
+    #[test]
+    fn warnings_flags_a_wrong_typed_tab_width() {
+        let data = YamlLoader::load_from_str("tab_width: \"four\"").unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        let warnings = preferences.warnings();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "tab_width");
+        assert_eq!(warnings[0].expected, "a positive integer");
+    }
+
+    #[test]
+    fn warnings_flags_an_unknown_top_level_key() {
+        let data = YamlLoader::load_from_str("bogus_setting: true").unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        let warnings = preferences.warnings();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "bogus_setting");
+        assert_eq!(warnings[0].expected, "a known configuration key");
+    }
+
+    #[test]
+    fn warnings_flags_a_wrong_typed_type_specific_tab_width() {
+        let data = YamlLoader::load_from_str("types:\n  rs:\n    tab_width: \"four\"").unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        let warnings = preferences.warnings();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "types.rs.tab_width");
+        assert_eq!(warnings[0].expected, "a positive integer");
+    }
+
+    #[test]
+    fn warnings_flags_a_wrong_typed_type_override() {
+        let data = YamlLoader::load_from_str("types:\n  rs: \"bogus\"").unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        let warnings = preferences.warnings();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "types.rs");
+        assert_eq!(warnings[0].expected, "a map of settings");
+    }
+
+    #[test]
+    fn warnings_is_empty_for_a_well_formed_document() {
+        let data = YamlLoader::load_from_str("theme: \"my_theme\"\ntab_width: 2\ntypes:\n  rs:\n    tab_width: 4")
+            .unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        assert!(preferences.warnings().is_empty());
+    }
+
+    #[test]
+    fn describe_documents_every_registered_option() {
+        let docs = Preferences::describe();
+
+        assert!(docs.iter().any(|doc| doc.name == "tab_width" && doc.type_hint == "<unsigned integer>" &&
+                                       doc.default == "2"));
+    }
+
+    #[test]
+    fn effective_values_reflects_overrides_and_falls_back_to_defaults() {
+        let data = YamlLoader::load_from_str("tab_width: 12").unwrap();
+        let preferences = Preferences::new(data.into_iter().nth(0));
+
+        let docs = Preferences::describe();
+        let values = preferences.effective_values();
+        let tab_width_index = docs.iter().position(|doc| doc.name == "tab_width").unwrap();
+        let theme_index = docs.iter().position(|doc| doc.name == "theme").unwrap();
+
+        assert_eq!(values[tab_width_index], "12");
+        assert_eq!(values[theme_index], "solarized_dark");
     }
 }